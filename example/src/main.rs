@@ -1,6 +1,7 @@
 use just_orm::{Identifiable, JsonDatabase};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use tracing::info;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct User {
@@ -13,6 +14,10 @@ impl Identifiable for User {
     fn get_id(&self) -> String {
         self.id.clone()
     }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,11 +31,14 @@ impl Identifiable for Product {
     fn get_id(&self) -> String {
         self.id.clone()
     }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
 }
 
 fn main() {
-    // Set the base directory for the database
-    JsonDatabase::set_base_dir("custom-dir");
+    tracing_subscriber::fmt::init();
 
     // Initialize the database with a model
     let mut user_db = JsonDatabase::<User>::new(Some("users"));
@@ -50,39 +58,45 @@ fn main() {
     };
 
     // Create users
-    user_db.create(&user1.id, user1.clone());
-    user_db.create(&user2.id, user2.clone());
+    user_db
+        .create(&user1.id, user1.clone())
+        .expect("valid user");
+    user_db
+        .create(&user2.id, user2.clone())
+        .expect("valid user");
 
     // Find a user by ID
     if let Some(user) = user_db.find_by_id("1") {
-        println!("Found user: {:?}", user);
+        info!(?user, "found user");
     } else {
-        println!("User not found");
+        info!("user not found");
     }
 
     // Update a user's information
     let update_data = json!({
         "name": "Johnathan Doe"
     });
-    user_db.update_by_id("1", update_data);
+    user_db
+        .update_by_id("1", update_data)
+        .expect("valid update");
 
     // Find all users
     let all_users = user_db.find_all();
-    println!("All users: {:?}", all_users);
+    info!(?all_users, "all users");
 
     // Find users by condition
     let condition = json!({
         "email": "jane.smith@example.com"
     });
     let found_users = user_db.find(&condition);
-    println!("Found users: {:?}", found_users);
+    info!(?found_users, "found users");
 
     // Delete a user by ID
     user_db.delete_by_id("2");
 
     // Find all users after deletion
     let all_users_after_deletion = user_db.find_all();
-    println!("All users after deletion: {:?}", all_users_after_deletion);
+    info!(?all_users_after_deletion, "all users after deletion");
 
     // Change model to "products"
     product_db.model("products");
@@ -101,10 +115,14 @@ fn main() {
     };
 
     // Create products
-    product_db.create(&product1.id, product1.clone());
-    product_db.create(&product2.id, product2.clone());
+    product_db
+        .create(&product1.id, product1.clone())
+        .expect("valid product");
+    product_db
+        .create(&product2.id, product2.clone())
+        .expect("valid product");
 
     // Find all products
     let all_products = product_db.find_all();
-    println!("All products: {:?}", all_products);
+    info!(?all_products, "all products");
 }
@@ -0,0 +1,280 @@
+//! An async, tokio-backed counterpart to `JsonDatabase`'s core CRUD and
+//! query surface, for use inside an async runtime (axum, actix, etc.) where
+//! blocking on `std::fs` would stall the executor. Gated behind the `async`
+//! feature so single-threaded CLI usage doesn't pull in tokio.
+
+use crate::{matches_condition, update_nested_object, Identifiable, BASE_DIR};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tracing::{debug, trace};
+
+/// How many record files `find_all` will read concurrently from disk.
+const MAX_CONCURRENT_READS: usize = 32;
+
+/// The async counterpart to `JsonDatabase`. Shares its query language
+/// (`find`/`matches_condition`) but every operation is backed by
+/// `tokio::fs`, and `find_all` loads matching files concurrently instead of
+/// one blocking read at a time.
+///
+/// This type only covers the core CRUD and query surface: unlike
+/// `JsonDatabase`, its reads and writes are plain `tokio::fs::read_to_string`/`write`
+/// calls with no crash-safe temp-file-plus-rename, no backup rotation, no
+/// file locking, no JSON Schema validation, no pluggable storage format, and
+/// no auto-generated ids or managed timestamps. Records written through
+/// `AsyncJsonDatabase` are not protected against a write being interrupted
+/// mid-way. Prefer `JsonDatabase` (e.g. via `tokio::task::spawn_blocking`)
+/// wherever those guarantees matter; use `AsyncJsonDatabase` only when
+/// avoiding a blocking call on the executor outweighs them.
+#[derive(Debug, Clone)]
+pub struct AsyncJsonDatabase<T> {
+    current_model_name: Option<String>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T> AsyncJsonDatabase<T>
+where
+    T: Serialize + for<'de> Deserialize<'de> + Clone + Identifiable + Send + Sync + 'static,
+{
+    /// Creates a new `AsyncJsonDatabase` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_name` - Optional model name to initialize the database with.
+    pub async fn new(model_name: Option<&str>) -> Self {
+        let path = match model_name {
+            Some(model_name) => Path::new(BASE_DIR).join(model_name),
+            None => Path::new(BASE_DIR).to_path_buf(),
+        };
+        create_directory_if_not_exists(&path).await;
+
+        AsyncJsonDatabase {
+            current_model_name: model_name.map(String::from),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Sets the model name for the database and ensures the directory exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_name` - The name of the model to use.
+    pub async fn model(&mut self, model_name: &str) -> &mut Self {
+        self.current_model_name = Some(model_name.to_string());
+        create_directory_if_not_exists(&self.get_model_path(model_name)).await;
+        self
+    }
+
+    /// Returns the path to the model directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_name` - The name of the model.
+    fn get_model_path(&self, model_name: &str) -> PathBuf {
+        Path::new(BASE_DIR).join(model_name)
+    }
+
+    /// Returns the path to a specific file in the model directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the file.
+    fn get_file_path(&self, id: &str) -> PathBuf {
+        let model_name = self
+            .current_model_name
+            .as_ref()
+            .expect("Model name is not specified");
+        self.get_model_path(model_name).join(format!("{}.json", id))
+    }
+
+    /// Returns a list of all JSON files in the model directory.
+    async fn get_all_files(&self) -> Vec<String> {
+        let model_name = self
+            .current_model_name
+            .as_ref()
+            .expect("Model name is not specified");
+        let model_path = self.get_model_path(model_name);
+        create_directory_if_not_exists(&model_path).await;
+
+        let mut entries = tokio::fs::read_dir(&model_path)
+            .await
+            .expect("Unable to read directory");
+        let mut files = Vec::new();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .expect("Unable to get directory entry")
+        {
+            let path = entry.path();
+            if path.extension().map_or(false, |ext| ext == "json") {
+                if let Some(name) = path.file_name() {
+                    files.push(name.to_string_lossy().into_owned());
+                }
+            }
+        }
+        files
+    }
+
+    /// Creates a new record in the database.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the record.
+    /// * `data` - The data to create.
+    pub async fn create(&self, id: &str, data: T) {
+        let file_path = self.get_file_path(id);
+        trace!(path = %file_path.display(), "creating record");
+        write_json_file(&file_path, &data).await;
+    }
+
+    /// Finds a record by ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the record to find.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(T)` if the record is found, or `None` if not.
+    pub async fn find_by_id(&self, id: &str) -> Option<T> {
+        let file_path = self.get_file_path(id);
+        read_json_file(&file_path).await
+    }
+
+    /// Updates a record by ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the record to update.
+    /// * `data` - The data to update.
+    pub async fn update_by_id(&self, id: &str, data: Value) {
+        let file_path = self.get_file_path(id);
+
+        if let Some(existing_data) = self.find_by_id(id).await {
+            let mut existing_json = serde_json::to_value(&existing_data).unwrap();
+            update_nested_object(&mut existing_json, &data);
+            let updated_data: T = serde_json::from_value(existing_json).unwrap();
+            write_json_file(&file_path, &updated_data).await;
+        }
+    }
+
+    /// Deletes a record by ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the record to delete.
+    pub async fn delete_by_id(&self, id: &str) {
+        let file_path = self.get_file_path(id);
+        if tokio::fs::try_exists(&file_path).await.unwrap_or(false) {
+            tokio::fs::remove_file(file_path)
+                .await
+                .expect("Unable to delete file");
+        }
+    }
+
+    /// Finds all records. Matching files are loaded concurrently, bounded by
+    /// `MAX_CONCURRENT_READS`, instead of one blocking read at a time.
+    ///
+    /// # Returns
+    ///
+    /// Returns a vector of all records.
+    pub async fn find_all(&self) -> Vec<T> {
+        let model_name = self
+            .current_model_name
+            .as_ref()
+            .expect("Model name is not specified");
+        let model_path = self.get_model_path(model_name);
+        let files = self.get_all_files().await;
+        debug!(count = files.len(), "scanning model directory");
+
+        stream::iter(files)
+            .map(|file| {
+                let path = model_path.join(file);
+                async move { read_json_file(&path).await }
+            })
+            .buffer_unordered(MAX_CONCURRENT_READS)
+            .filter_map(|item| async move { item })
+            .collect()
+            .await
+    }
+
+    /// Finds records matching a condition.
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The condition to match.
+    ///
+    /// # Returns
+    ///
+    /// Returns a vector of matching records.
+    pub async fn find(&self, condition: &Value) -> Vec<T> {
+        self.find_all()
+            .await
+            .into_iter()
+            .filter(|item| matches_condition(&serde_json::to_value(item).unwrap(), condition))
+            .collect()
+    }
+
+    /// Finds the first record matching a condition.
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The condition to match.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(T)` if a matching record is found, or `None` if not.
+    pub async fn find_one(&self, condition: &Value) -> Option<T> {
+        self.find_all()
+            .await
+            .into_iter()
+            .find(|item| matches_condition(&serde_json::to_value(item).unwrap(), condition))
+    }
+}
+
+/// Creates a directory if it does not exist.
+///
+/// # Arguments
+///
+/// * `path` - The path of the directory to create.
+async fn create_directory_if_not_exists(path: &Path) {
+    if !tokio::fs::try_exists(path).await.unwrap_or(false) {
+        tokio::fs::create_dir_all(path)
+            .await
+            .expect("Unable to create directory");
+    }
+}
+
+/// Reads a JSON file and deserializes it into a Rust struct.
+///
+/// # Arguments
+///
+/// * `path` - The path of the JSON file to read.
+///
+/// # Returns
+///
+/// Returns `Some(T)` if successful, or `None` if there is an error.
+async fn read_json_file<T>(path: &Path) -> Option<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let contents = tokio::fs::read_to_string(path).await.ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Serializes a Rust struct into JSON and writes it to a file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to write.
+/// * `data` - The data to serialize and write.
+async fn write_json_file<T>(path: &Path, data: &T)
+where
+    T: Serialize,
+{
+    let contents = serde_json::to_string_pretty(data).expect("Unable to serialize data");
+    tokio::fs::write(path, contents)
+        .await
+        .expect("Unable to write to file");
+}